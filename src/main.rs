@@ -1,21 +1,28 @@
 use std::{env, fs};
+use std::collections::{HashMap, HashSet};
 use std::fs::FileType;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::channel;
 
 use askama_axum::Template;
 use axum::{
+    extract::Multipart,
     http::StatusCode,
     Json,
     Router, routing::{get, post},
 };
+use axum::body::Body;
+use axum::http::header;
 use axum::http::{Response, Uri};
 use axum::response::{Html, IntoResponse};
 use image::imageops::thumbnail;
+use image::GenericImageView;
 use notify::{Event, EventKind, recommended_watcher, RecursiveMode, Watcher};
-use notify::event::CreateKind;
+use notify::event::{CreateKind, ModifyKind, RemoveKind, RenameMode};
 use once_cell::sync::Lazy;
-use tower_http::services::ServeDir;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, Mutex, Semaphore};
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 
@@ -23,6 +30,9 @@ struct Config {
     static_folder: String,
     image_folder: String,
     thumbnail_folder: String,
+    thumbnailer_concurrency: usize,
+    thumbnail_widths: Vec<u32>,
+    upload_max_bytes: usize,
 }
 
 static CONFIG: Lazy<Config> = Lazy::new(|| {
@@ -31,9 +41,246 @@ static CONFIG: Lazy<Config> = Lazy::new(|| {
         static_folder: env::var("STATIC_FOLDER").expect("STATIC_FOLDER must be set"),
         image_folder: env::var("IMAGE_FOLDER").expect("IMAGE_FOLDER must be set"),
         thumbnail_folder: env::var("THUMBNAIL_FOLDER").expect("THUMBNAIL_FOLDER must be set"),
+        thumbnailer_concurrency: env::var("THUMBNAILER_CONCURRENCY")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)),
+        thumbnail_widths: env::var("THUMBNAIL_WIDTHS")
+            .ok()
+            .map(|v| v.split(',').filter_map(|w| w.trim().parse().ok()).collect())
+            .filter(|widths: &Vec<u32>| !widths.is_empty())
+            .unwrap_or_else(|| vec![150, 300, 600]),
+        upload_max_bytes: env::var("UPLOAD_MAX_BYTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(50 * 1024 * 1024),
     }
 });
 
+/// Long-lived actor that runs thumbnail generation with bounded concurrency
+/// (a semaphore) so a burst of file events can't saturate the machine.
+///
+/// Both the startup scan and the notify watcher just push paths onto
+/// `sender`; the actor owns deduplication and the actual decode/encode work.
+struct Thumbnailer {
+    sender: mpsc::UnboundedSender<PathBuf>,
+}
+
+impl Thumbnailer {
+    fn spawn() -> Self {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<PathBuf>();
+        let semaphore = std::sync::Arc::new(Semaphore::new(CONFIG.thumbnailer_concurrency));
+        let pending = std::sync::Arc::new(Mutex::new(HashSet::<PathBuf>::new()));
+
+        tokio::spawn(async move {
+            while let Some(path) = receiver.recv().await {
+                {
+                    let mut pending = pending.lock().await;
+                    if !pending.insert(path.clone()) {
+                        continue;
+                    }
+                }
+
+                let semaphore = semaphore.clone();
+                let pending = pending.clone();
+                tokio::spawn(async move {
+                    let _permit = semaphore.acquire().await.unwrap();
+
+                    let job_path = path.clone();
+                    tokio::task::spawn_blocking(move || {
+                        let hash = hash_for_image(&job_path);
+                        write_metadata_sidecar(&job_path, &hash);
+                        for &width in &CONFIG.thumbnail_widths {
+                            let thumbnail_path = thumbnail_path_for(&job_path, width);
+                            if !thumbnail_path.exists() {
+                                create_thumbnail(&job_path, &thumbnail_path, width);
+                            }
+                        }
+                    })
+                    .await
+                    .unwrap();
+
+                    pending.lock().await.remove(&path);
+                });
+            }
+        });
+
+        Thumbnailer { sender }
+    }
+
+    fn enqueue(&self, path: PathBuf) {
+        if self.sender.send(path).is_err() {
+            tracing::error!("Thumbnailer actor has shut down, dropping job");
+        }
+    }
+}
+
+static THUMBNAILER: Lazy<Thumbnailer> = Lazy::new(Thumbnailer::spawn);
+
+/// Maps an image path (relative to `CONFIG.image_folder`) to the content hash
+/// its thumbnail is stored under, so handlers and the watcher always agree on
+/// where a given image's thumbnail lives without re-hashing on every lookup.
+static IMAGE_HASHES: Lazy<std::sync::Mutex<HashMap<PathBuf, String>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+fn relative_image_path(path: &Path) -> PathBuf {
+    path.strip_prefix(&CONFIG.image_folder).unwrap_or(path).to_path_buf()
+}
+
+/// Content-addresses an image by blake3-hashing its path relative to
+/// `CONFIG.image_folder`, not its bytes. Hashing the path keeps the hash
+/// recoverable after the source file is gone (delete/rename cleanup can
+/// always derive it, with no dependency on a cache surviving a restart) at
+/// the cost of not deduplicating identical files under different paths,
+/// which the request explicitly allowed as a fallback.
+fn hash_for_image(path: &Path) -> String {
+    let relative = relative_image_path(path);
+
+    if let Some(hash) = IMAGE_HASHES.lock().unwrap().get(&relative) {
+        return hash.clone();
+    }
+
+    let hash = blake3::hash(relative.to_string_lossy().as_bytes()).to_hex().to_string();
+    IMAGE_HASHES.lock().unwrap().insert(relative, hash.clone());
+    hash
+}
+
+/// Deletes any previously generated thumbnails/metadata for `path`. Since
+/// the hash is path-derived (not content-derived), overwriting an existing
+/// file reuses the same hash and would otherwise keep serving the old
+/// thumbnail and sidecar; callers that replace a file's contents (e.g. the
+/// upload handler) must call this before re-enqueuing it.
+fn invalidate_thumbnails_for(path: &Path) {
+    let dir = Path::new(&CONFIG.thumbnail_folder).join(hash_for_image(path));
+    if let Err(err) = fs::remove_dir_all(&dir) {
+        if err.kind() != std::io::ErrorKind::NotFound {
+            tracing::warn!("Failed to invalidate stale thumbnails at {:?}: {}", dir, err);
+        }
+    }
+}
+
+/// Deletes every generated size for `path`. The hash is path-derived, so it
+/// can always be recomputed here even if this particular file was never
+/// enqueued this session (e.g. its thumbnails already existed at startup, or
+/// the process restarted since) — no persisted cache is needed for cleanup
+/// to find the right directory.
+fn remove_thumbnails_for(path: &Path) {
+    IMAGE_HASHES.lock().unwrap().remove(&relative_image_path(path));
+
+    let dir = Path::new(&CONFIG.thumbnail_folder).join(hash_for_image(path));
+    if let Err(err) = fs::remove_dir_all(&dir) {
+        if err.kind() != std::io::ErrorKind::NotFound {
+            tracing::warn!("Failed to remove thumbnails at {:?}: {}", dir, err);
+        }
+    }
+}
+
+fn thumbnail_path_for(path: &Path, width: u32) -> PathBuf {
+    Path::new(&CONFIG.thumbnail_folder)
+        .join(hash_for_image(path))
+        .join(width.to_string())
+        .with_extension("webp")
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct ImageMetadata {
+    camera: Option<String>,
+    taken_at: Option<String>,
+    width: u32,
+    height: u32,
+}
+
+fn metadata_sidecar_path(hash: &str) -> PathBuf {
+    Path::new(&CONFIG.thumbnail_folder).join(hash).join("info.json")
+}
+
+fn extract_metadata(image_path: &Path) -> ImageMetadata {
+    let mut metadata = ImageMetadata::default();
+
+    if let Ok(img) = image::open(image_path) {
+        let (width, height) = img.dimensions();
+        metadata.width = width;
+        metadata.height = height;
+    }
+
+    if let Ok(file) = fs::File::open(image_path) {
+        let mut reader = std::io::BufReader::new(file);
+        if let Ok(exif) = exif::Reader::new().read_from_container(&mut reader) {
+            metadata.camera = exif
+                .get_field(exif::Tag::Model, exif::In::PRIMARY)
+                .map(|field| field.display_value().to_string());
+            metadata.taken_at = exif
+                .get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)
+                .map(|field| field.display_value().to_string());
+        }
+    }
+
+    metadata
+}
+
+/// Writes the metadata sidecar once per image hash; cheap no-op on every
+/// subsequent enqueue of the same file (e.g. re-generating a missing width).
+fn write_metadata_sidecar(image_path: &Path, hash: &str) {
+    let sidecar_path = metadata_sidecar_path(hash);
+    if sidecar_path.exists() {
+        return;
+    }
+
+    let metadata = extract_metadata(image_path);
+    if let Some(parent) = sidecar_path.parent() {
+        fs::create_dir_all(parent).unwrap();
+    }
+    if let Ok(json) = serde_json::to_string(&metadata) {
+        let _ = fs::write(sidecar_path, json);
+    }
+}
+
+fn read_metadata_sidecar(hash: &str) -> Option<ImageMetadata> {
+    let bytes = fs::read(metadata_sidecar_path(hash)).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+fn read_exif_orientation(image_path: &Path) -> u32 {
+    let Ok(file) = fs::File::open(image_path) else { return 1 };
+    let mut reader = std::io::BufReader::new(file);
+    exif::Reader::new()
+        .read_from_container(&mut reader)
+        .ok()
+        .and_then(|exif| exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY).and_then(|f| f.value.get_uint(0)))
+        .unwrap_or(1)
+}
+
+/// Rotates/flips a decoded image per the EXIF orientation tag (values 1-8)
+/// so portrait photos from phones aren't shown sideways.
+fn apply_orientation(img: image::DynamicImage, orientation: u32) -> image::DynamicImage {
+    match orientation {
+        2 => img.fliph(),
+        3 => img.rotate180(),
+        4 => img.flipv(),
+        5 => img.rotate90().fliph(),
+        6 => img.rotate90(),
+        7 => img.rotate270().fliph(),
+        8 => img.rotate270(),
+        _ => img,
+    }
+}
+
+/// Builds the `src`/`srcset` pair for an image, in ascending width order, so
+/// the browser can pick the resolution that matches the viewport/DPI.
+fn thumbnail_urls_for(path: &Path) -> (String, String) {
+    let hash = hash_for_image(path);
+    let mut widths = CONFIG.thumbnail_widths.clone();
+    widths.sort_unstable();
+    let srcset = widths
+        .iter()
+        .map(|width| format!("/static/thumbnails/{}/{}.webp {}w", hash, width, width))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let smallest = widths.first().copied().unwrap_or(150);
+    let default = format!("/static/thumbnails/{}/{}.webp", hash, smallest);
+    (default, srcset)
+}
+
 
 #[tokio::main]
 async fn main() {
@@ -48,18 +295,19 @@ async fn main() {
 
     init_directories();
 
-    let serve_dir = ServeDir::new(&CONFIG.static_folder);
-
-    generate_startup_thumbnails_for_dir(&Path::new(&CONFIG.image_folder));
+    Lazy::force(&THUMBNAILER);
+    tokio::task::spawn_blocking(|| generate_startup_thumbnails_for_dir(&Path::new(&CONFIG.image_folder)));
 
     tokio::spawn(monitor_directory());
 
-    tracing::info!("Initialized thumbnails and started monitoring directory");
+    tracing::info!("Thumbnailer started, server is ready while thumbnails fill in");
 
     let app = Router::new()
         .route("/gallery/", get(root))
         .route("/gallery/{*path}", get(gallery))
-        .nest_service("/static", serve_dir);
+        .route("/gallery/{*path}", post(upload))
+        .route("/static/{*path}", get(serve))
+        .layer(axum::extract::DefaultBodyLimit::max(CONFIG.upload_max_bytes));
 
     tracing::info!("Server started on port 3000");
 
@@ -70,7 +318,11 @@ async fn main() {
 struct Image {
     original: String,
     thumbnail: String,
+    /// Comma-separated `srcset` candidates, empty for folder tiles which only
+    /// ever have a single icon.
+    srcset: String,
     name: String,
+    metadata: Option<ImageMetadata>,
 }
 
 macro_rules! extract_file_name {
@@ -97,14 +349,15 @@ async fn root() -> impl IntoResponse {
                 let file_name = e.file_name().into_string().unwrap();
 
                 if file_type.is_file() {
-                    let file_name_webp = Path::new(&file_name).with_extension("webp");
+                    let full_path = Path::new(&CONFIG.image_folder).join(&file_name);
+                    let (thumbnail, srcset) = thumbnail_urls_for(&full_path);
+                    let metadata = read_metadata_sidecar(&hash_for_image(&full_path));
                     let original = format!("/static/images/{}", file_name);
-                    let thumbnail = format!("/static/thumbnails/{}", file_name_webp.display());
-                    Some(Image { original, thumbnail, name: file_name })
+                    Some(Image { original, thumbnail, srcset, name: file_name, metadata })
                 } else if file_type.is_dir() {
                     let folder_path = format!("/gallery/{}", file_name);
                     let thumbnail = "/static/assets/folder.svg".to_string();
-                    Some(Image { original: folder_path, thumbnail, name: file_name })
+                    Some(Image { original: folder_path, thumbnail, srcset: String::new(), name: file_name, metadata: None })
                 } else {
                     None
                 }
@@ -117,7 +370,24 @@ async fn root() -> impl IntoResponse {
 }
 
 async fn gallery(axum::extract::Path(path): axum::extract::Path<String>) -> impl IntoResponse {
-    let dir = fs::read_dir(Path::new(&CONFIG.image_folder).join(&path)).unwrap();
+    // `/gallery/{*path}` can't be followed by a literal `/info` segment as a
+    // separate route registration — axum's wildcard capture must be the last
+    // component of its own route. So a path ending in `/info` is only
+    // treated as a metadata lookup when there's no such directory on disk
+    // and the part before `/info` is an image file;
+    // an actual `.../info` directory is still listed normally below.
+    if !Path::new(&CONFIG.image_folder).join(&path).is_dir() {
+        if let Some(image_path) = path.strip_suffix("/info") {
+            let full_image_path = Path::new(&CONFIG.image_folder).join(image_path);
+            if full_image_path.is_file() {
+                return image_info(image_path.to_string()).await.into_response();
+            }
+        }
+    }
+
+    let Ok(dir) = fs::read_dir(Path::new(&CONFIG.image_folder).join(&path)) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
     let mut images = dir
         .filter_map(|entry| {
             entry.ok().and_then(|e| {
@@ -125,14 +395,15 @@ async fn gallery(axum::extract::Path(path): axum::extract::Path<String>) -> impl
                 let file_name = e.file_name().into_string().unwrap();
 
                 if file_type.is_file() {
-                    let file_name_webp = Path::new(&file_name).with_extension("webp");
+                    let full_path = Path::new(&CONFIG.image_folder).join(&path).join(&file_name);
+                    let (thumbnail, srcset) = thumbnail_urls_for(&full_path);
+                    let metadata = read_metadata_sidecar(&hash_for_image(&full_path));
                     let original = format!("/static/images/{}/{}", path, file_name);
-                    let thumbnail = format!("/static/thumbnails/{}", file_name_webp.display());
-                    Some(Image { original, thumbnail, name: file_name })
+                    Some(Image { original, thumbnail, srcset, name: file_name, metadata })
                 } else if file_type.is_dir() {
                     let folder_path = format!("/gallery/{}/{}", path, file_name);
                     let thumbnail = "/static/assets/folder.svg".to_string();
-                    Some(Image { original: folder_path, thumbnail, name: file_name })
+                    Some(Image { original: folder_path, thumbnail, srcset: String::new(), name: file_name, metadata: None })
                 } else {
                     None
                 }
@@ -144,6 +415,304 @@ async fn gallery(axum::extract::Path(path): axum::extract::Path<String>) -> impl
     Html(template.render().unwrap()).into_response()
 }
 
+/// `GET /gallery/{*path}/info` — returns the cached EXIF-derived metadata
+/// sidecar for a single image, or 404 if it hasn't been generated yet.
+async fn image_info(path: String) -> impl IntoResponse {
+    let full_path = Path::new(&CONFIG.image_folder).join(&path);
+    match read_metadata_sidecar(&hash_for_image(&full_path)) {
+        Some(metadata) => Json(metadata).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[derive(Serialize)]
+struct UploadError {
+    message: String,
+}
+
+fn upload_error(status: StatusCode, message: &str) -> axum::response::Response {
+    (status, Json(UploadError { message: message.to_string() })).into_response()
+}
+
+/// True if every component of `raw` is a plain path segment — rejects `..`,
+/// absolute paths, and (on Windows) drive prefixes — so it can't be used to
+/// escape whatever directory it gets joined onto.
+fn is_safe_relative_path(raw: &str) -> bool {
+    Path::new(raw).components().all(|c| matches!(c, std::path::Component::Normal(_)))
+}
+
+/// Like `is_safe_relative_path`, but also requires a single segment, since a
+/// filename should never itself carry directory components.
+fn is_safe_file_name(raw: &str) -> bool {
+    Path::new(raw).components().count() == 1 && is_safe_relative_path(raw)
+}
+
+/// Joins `relative` onto `root` and canonicalizes the result, rejecting
+/// anything that escapes `root` (e.g. `..` traversal, or a symlink that
+/// walks outside of it) instead of trusting the raw path from the URL.
+fn safe_join(root: &Path, relative: &str) -> Option<PathBuf> {
+    let root = root.canonicalize().ok()?;
+    let candidate = root.join(relative).canonicalize().ok()?;
+    candidate.starts_with(&root).then_some(candidate)
+}
+
+/// `POST /gallery/{*path}` — streams a multipart file upload into the
+/// corresponding subfolder of `IMAGE_FOLDER` and queues it for thumbnailing.
+/// Both the subfolder (from the URL wildcard) and the filename (from the
+/// multipart part) are validated against traversal before touching disk, and
+/// the body is written chunk-by-chunk rather than buffered whole in memory.
+/// Anything whose magic bytes don't decode as a supported image is rejected
+/// instead of trusting the declared content type.
+async fn upload(
+    axum::extract::Path(path): axum::extract::Path<String>,
+    mut multipart: Multipart,
+) -> impl IntoResponse {
+    if !is_safe_relative_path(&path) {
+        return upload_error(StatusCode::BAD_REQUEST, "invalid path");
+    }
+
+    let mut field = match multipart.next_field().await {
+        Ok(Some(field)) => field,
+        Ok(None) => return upload_error(StatusCode::BAD_REQUEST, "no file in upload"),
+        Err(_) => return upload_error(StatusCode::BAD_REQUEST, "malformed multipart body"),
+    };
+
+    let Some(file_name) = field.file_name().map(str::to_string) else {
+        return upload_error(StatusCode::BAD_REQUEST, "missing filename");
+    };
+    if !is_safe_file_name(&file_name) {
+        return upload_error(StatusCode::BAD_REQUEST, "invalid filename");
+    }
+
+    let dest_dir = Path::new(&CONFIG.image_folder).join(&path);
+    if let Err(err) = fs::create_dir_all(&dest_dir) {
+        tracing::error!("Failed to create upload directory {:?}: {}", dest_dir, err);
+        return upload_error(StatusCode::INTERNAL_SERVER_ERROR, "failed to save upload");
+    }
+
+    // Confine the destination under IMAGE_FOLDER using the canonicalized
+    // path only as a containment check. The path we actually write to (and
+    // later hash) stays non-canonical and relative to IMAGE_FOLDER, like
+    // every other caller of hash_for_image (root, gallery, image_info, the
+    // watcher) — feeding hash_for_image a canonicalized absolute path here
+    // would make relative_image_path's strip_prefix fail whenever
+    // IMAGE_FOLDER is itself relative, hashing this upload to a different
+    // directory than everything else reads from.
+    if safe_join(Path::new(&CONFIG.image_folder), &path).is_none() {
+        return upload_error(StatusCode::BAD_REQUEST, "invalid path");
+    }
+    let dest_path = dest_dir.join(&file_name);
+
+    let mut file = match tokio::fs::File::create(&dest_path).await {
+        Ok(file) => file,
+        Err(err) => {
+            tracing::error!("Failed to create upload destination {:?}: {}", dest_path, err);
+            return upload_error(StatusCode::INTERNAL_SERVER_ERROR, "failed to save upload");
+        }
+    };
+
+    let mut sniffed_ok = false;
+    let mut wrote_any = false;
+    loop {
+        let chunk = match field.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(_) => {
+                drop(file);
+                let _ = tokio::fs::remove_file(&dest_path).await;
+                return upload_error(StatusCode::BAD_REQUEST, "failed to read upload body");
+            }
+        };
+
+        if !sniffed_ok {
+            if image::guess_format(&chunk).is_err() {
+                drop(file);
+                let _ = tokio::fs::remove_file(&dest_path).await;
+                return upload_error(StatusCode::UNSUPPORTED_MEDIA_TYPE, "file is not a supported image type");
+            }
+            sniffed_ok = true;
+        }
+
+        if file.write_all(&chunk).await.is_err() {
+            drop(file);
+            let _ = tokio::fs::remove_file(&dest_path).await;
+            return upload_error(StatusCode::INTERNAL_SERVER_ERROR, "failed to save upload");
+        }
+        wrote_any = true;
+    }
+
+    if !wrote_any {
+        let _ = tokio::fs::remove_file(&dest_path).await;
+        return upload_error(StatusCode::BAD_REQUEST, "empty upload");
+    }
+
+    tracing::info!("Saved upload {:?}, queueing thumbnail", dest_path);
+    invalidate_thumbnails_for(&dest_path);
+    THUMBNAILER.enqueue(dest_path);
+
+    StatusCode::CREATED.into_response()
+}
+
+/// Parses a single-range `Range: bytes=start-end` header against a known
+/// total length. Multi-range requests aren't supported; callers fall back to
+/// serving the whole file.
+fn parse_range(header_value: &str, total_len: u64) -> Option<(u64, u64)> {
+    let spec = header_value.strip_prefix("bytes=")?;
+    let (start, end) = spec.split_once('-')?;
+
+    // An empty start is a suffix range ("bytes=-500" means "the last 500
+    // bytes"), not "start from 0" — those mean opposite ends of the file.
+    if start.is_empty() {
+        let suffix_len: u64 = end.parse().ok()?;
+        if suffix_len == 0 || suffix_len > total_len {
+            return None;
+        }
+        return Some((total_len - suffix_len, total_len - 1));
+    }
+
+    let start: u64 = start.parse().ok()?;
+    let end: u64 = if end.is_empty() { total_len.saturating_sub(1) } else { end.parse().ok()? };
+
+    if start > end || end >= total_len {
+        None
+    } else {
+        Some((start, end))
+    }
+}
+
+fn modified_secs(time: std::time::SystemTime) -> u64 {
+    time.duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn parse_http_date_header(headers: &axum::http::HeaderMap, name: axum::http::HeaderName) -> Option<std::time::SystemTime> {
+    httpdate::parse_http_date(headers.get(name)?.to_str().ok()?).ok()
+}
+
+/// Reads only `start..=end` of `path` instead of loading the whole file,
+/// which matters for range requests against large originals.
+fn read_range(path: &Path, start: u64, end: u64) -> std::io::Result<Vec<u8>> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut file = fs::File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut buf = vec![0u8; (end - start + 1) as usize];
+    file.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// `GET /static/{*path}` — replaces the old blanket `ServeDir` mount so we
+/// can canonicalize and bounds-check the requested path, emit caching
+/// headers appropriate to the asset kind, honor conditional requests, and
+/// serve only the requested byte range for large originals instead of
+/// always reading and sending the whole file.
+async fn serve(axum::extract::Path(path): axum::extract::Path<String>, headers: axum::http::HeaderMap) -> impl IntoResponse {
+    let (root, relative, is_thumbnail) = if let Some(rest) = path.strip_prefix("images/") {
+        (Path::new(&CONFIG.image_folder), rest, false)
+    } else if let Some(rest) = path.strip_prefix("thumbnails/") {
+        (Path::new(&CONFIG.thumbnail_folder), rest, true)
+    } else {
+        (Path::new(&CONFIG.static_folder), path.as_str(), false)
+    };
+
+    let Some(file_path) = safe_join(root, relative) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let Ok(metadata) = fs::metadata(&file_path) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    if !metadata.is_file() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let total_len = metadata.len();
+    let modified = metadata.modified().ok();
+    let last_modified = modified.map(httpdate::fmt_http_date);
+    // Thumbnail URLs are keyed by hash_for_image's path-derived hash, not a
+    // content hash, so the same URL can start serving different bytes after
+    // an overwrite. They're cacheable but not `immutable`, and get a short
+    // max-age so a re-upload's invalidate_thumbnails_for() is visible to
+    // clients in a reasonable time instead of up to a year.
+    let cache_control = if is_thumbnail {
+        "public, max-age=300"
+    } else {
+        "public, max-age=3600"
+    };
+
+    if let (Some(modified), Some(if_modified_since)) =
+        (modified, parse_http_date_header(&headers, header::IF_MODIFIED_SINCE))
+    {
+        if modified_secs(modified) <= modified_secs(if_modified_since) {
+            let mut builder = Response::builder().status(StatusCode::NOT_MODIFIED).header(header::CACHE_CONTROL, cache_control);
+            if let Some(last_modified) = &last_modified {
+                builder = builder.header(header::LAST_MODIFIED, last_modified.clone());
+            }
+            return builder.body(Body::empty()).unwrap().into_response();
+        }
+    }
+
+    let content_type = mime_guess::from_path(&file_path).first_or_octet_stream().to_string();
+
+    // A Range request is only honored if there's no If-Range, or If-Range
+    // names a time at or after the file's current Last-Modified — otherwise
+    // the range would be computed against content the client no longer has.
+    let if_range_satisfied = match headers.get(header::IF_RANGE) {
+        Some(_) => match (modified, parse_http_date_header(&headers, header::IF_RANGE)) {
+            (Some(modified), Some(if_range)) => modified_secs(modified) <= modified_secs(if_range),
+            _ => false,
+        },
+        None => true,
+    };
+    // Multi-range requests aren't supported (see parse_range): those are
+    // answered with the whole file rather than a 416.
+    let range = if if_range_satisfied {
+        headers
+            .get(header::RANGE)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| !value.contains(','))
+    } else {
+        None
+    };
+
+    if let Some(range_header) = range {
+        return match parse_range(range_header, total_len) {
+            Some((start, end)) => match read_range(&file_path, start, end) {
+                Ok(bytes) => {
+                    let mut builder = Response::builder()
+                        .status(StatusCode::PARTIAL_CONTENT)
+                        .header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, total_len))
+                        .header(header::CONTENT_TYPE, content_type)
+                        .header(header::ACCEPT_RANGES, "bytes")
+                        .header(header::CACHE_CONTROL, cache_control);
+                    if let Some(last_modified) = last_modified {
+                        builder = builder.header(header::LAST_MODIFIED, last_modified);
+                    }
+                    builder.body(Body::from(bytes)).unwrap().into_response()
+                }
+                Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            },
+            None => Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", total_len))
+                .body(Body::empty())
+                .unwrap()
+                .into_response(),
+        };
+    }
+
+    let Ok(bytes) = fs::read(&file_path) else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let mut builder = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::CACHE_CONTROL, cache_control);
+    if let Some(last_modified) = last_modified {
+        builder = builder.header(header::LAST_MODIFIED, last_modified);
+    }
+    builder.body(Body::from(bytes)).unwrap().into_response()
+}
 
 async fn monitor_directory() {
     let (tx, rx) = channel();
@@ -151,13 +720,39 @@ async fn monitor_directory() {
     watcher.watch(Path::new(&CONFIG.image_folder), RecursiveMode::Recursive).unwrap();
 
     while let Ok(Ok(event)) = rx.recv() {
-        if let EventKind::Create(CreateKind::Any) = event.kind {
-            if let Some(path) = event.paths.first() {
-                let file_name = path.file_name().unwrap();
-                let thumbnail_path = Path::new(&CONFIG.thumbnail_folder).join(file_name);
-                tracing::info!("Creating thumbnail for newly found file {:?}", file_name);
-                create_thumbnail(path, &thumbnail_path);
+        match event.kind {
+            EventKind::Create(CreateKind::Any) => {
+                if let Some(path) = event.paths.first() {
+                    tracing::info!("Queueing thumbnail for newly found file {:?}", path.file_name());
+                    THUMBNAILER.enqueue(path.clone());
+                }
             }
+            EventKind::Remove(RemoveKind::Any) => {
+                if let Some(path) = event.paths.first() {
+                    tracing::info!("Removing thumbnails for deleted file {:?}", path.file_name());
+                    remove_thumbnails_for(path);
+                }
+            }
+            // Some platforms deliver a rename as one `Both` event carrying
+            // both paths; others deliver separate `From`/`To` events instead.
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+                if let [from, to] = event.paths.as_slice() {
+                    tracing::info!("Moving thumbnails for renamed file {:?} -> {:?}", from, to);
+                    remove_thumbnails_for(from);
+                    THUMBNAILER.enqueue(to.clone());
+                }
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                if let Some(path) = event.paths.first() {
+                    remove_thumbnails_for(path);
+                }
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                if let Some(path) = event.paths.first() {
+                    THUMBNAILER.enqueue(path.clone());
+                }
+            }
+            _ => {}
         }
     }
 }
@@ -185,22 +780,22 @@ fn generate_startup_thumbnails_for_dir(dir: &Path) {
     dir.for_each(|entry| {
         let entry = entry.unwrap();
         let file_type = entry.file_type().unwrap();
-        let file_name = entry.file_name();
         let path = entry.path();
         if file_type.is_dir() {
             generate_startup_thumbnails_for_dir(&path);
-        } else {
-            let thumbnail_path = Path::new(&CONFIG.thumbnail_folder).join(file_name).with_extension("webp");
-            if !thumbnail_path.exists() {
-                create_thumbnail(&path, &thumbnail_path);
-            }
+        } else if CONFIG.thumbnail_widths.iter().any(|&width| !thumbnail_path_for(&path, width).exists()) {
+            THUMBNAILER.enqueue(path);
         }
     });
 }
 
-fn create_thumbnail(image_path: &Path, output_path: &Path) {
+fn create_thumbnail(image_path: &Path, output_path: &Path, width: u32) {
     if let Ok(img) = image::open(image_path) {
-        let thumb = img.resize(150, 150, image::imageops::FilterType::Lanczos3);
+        let img = apply_orientation(img, read_exif_orientation(image_path));
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        let thumb = img.resize(width, width, image::imageops::FilterType::Lanczos3);
         let webp_path = output_path.with_extension("webp");
         thumb.save(webp_path).unwrap();
     }